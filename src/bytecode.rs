@@ -0,0 +1,118 @@
+use crate::codegen::{Instr, Operand};
+
+const MAGIC: [u8; 3] = *b"ALB";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+const WORD_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytecodeError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    TruncatedWord,
+    Unresolved,
+    UnknownOpcode(u8),
+}
+
+/// Serializes an already-linked program (`Instr::Op` only, no `Label`/`Branch`
+/// left) into fixed 4-byte words behind a short magic/version header.
+pub fn encode(program: &[Instr]) -> Result<Vec<u8>, BytecodeError> {
+    let mut out = Vec::with_capacity(HEADER_LEN + program.len() * WORD_LEN);
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    for instr in program {
+        let Instr::Op { op, a, b, dest } = instr else {
+            return Err(BytecodeError::Unresolved);
+        };
+        let dest = match dest {
+            Operand::Reg(n) | Operand::Addr(n) => *n,
+        };
+        out.extend_from_slice(&[*op, *a, *b, dest]);
+    }
+    Ok(out)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instr>, BytecodeError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(BytecodeError::TruncatedWord);
+    }
+    if bytes[..MAGIC.len()] != MAGIC {
+        return Err(BytecodeError::BadMagic);
+    }
+    let version = bytes[MAGIC.len()];
+    if version != VERSION {
+        return Err(BytecodeError::UnsupportedVersion(version));
+    }
+
+    let body = &bytes[HEADER_LEN..];
+    if !body.len().is_multiple_of(WORD_LEN) {
+        return Err(BytecodeError::TruncatedWord);
+    }
+    body.chunks_exact(WORD_LEN)
+        .map(|word| {
+            let [op, a, b, dest] = word else {
+                unreachable!("chunks_exact(4) always yields 4-byte slices")
+            };
+            validate_opcode(*op)?;
+            Ok(Instr::Op {
+                op: *op,
+                a: *a,
+                b: *b,
+                dest: dest_operand(*op, *dest),
+            })
+        })
+        .collect()
+}
+
+fn validate_opcode(op: u8) -> Result<(), BytecodeError> {
+    // The immediate-mode flag bits (128, 64) sit above the 6-bit base opcode.
+    let base = op & 0b0011_1111;
+    if base <= 21 {
+        Ok(())
+    } else {
+        Err(BytecodeError::UnknownOpcode(op))
+    }
+}
+
+// The `Cmp` range (16..=21) is the only family linked into `Operand::Addr`
+// (a resolved branch offset); every other opcode writes a register dest.
+fn dest_operand(op: u8, n: u8) -> Operand {
+    let base = op & 0b0011_1111;
+    if (16..=21).contains(&base) {
+        Operand::Addr(n)
+    } else {
+        Operand::Reg(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips() {
+        let program = vec![
+            Instr::Op {
+                op: 0,
+                a: 1,
+                b: 2,
+                dest: Operand::Reg(3),
+            },
+            Instr::Op {
+                op: 16,
+                a: 0,
+                b: 0,
+                dest: Operand::Addr(200),
+            },
+        ];
+        let bytes = encode(&program).unwrap();
+        assert_eq!(decode(&bytes).unwrap(), program);
+    }
+
+    #[test]
+    fn rejects_unknown_opcode() {
+        let mut bytes = vec![b'A', b'L', b'B', VERSION];
+        bytes.extend_from_slice(&[22, 0, 0, 0]);
+        assert_eq!(decode(&bytes), Err(BytecodeError::UnknownOpcode(22)));
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt::{self, Display};
+
+use crate::syntax::{fix_b, fix_u, Bop, Cmp, Reg, Stmt, ToNum, Val, HF, LOAD, SAVE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Reg(u8),
+    Addr(u8),
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Reg(n) | Operand::Addr(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instr {
+    Op {
+        op: u8,
+        a: u8,
+        b: u8,
+        dest: Operand,
+    },
+    Label(String),
+    Branch {
+        op: u8,
+        a: u8,
+        b: u8,
+        target: String,
+    },
+}
+
+impl Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Op { op, a, b, dest } => write!(f, "{op} {a} {b} {dest}"),
+            Instr::Label(name) => write!(f, "label {name}"),
+            Instr::Branch { op, a, b, target } => write!(f, "{op} {a} {b} {target}"),
+        }
+    }
+}
+
+/// Lowers a `Stmt` tree into a flat `Instr` sequence, minting unique label
+/// names from a monotonic counter instead of `Display`'s wall-clock stamps —
+/// the same program lowers to the same output every time.
+#[derive(Default)]
+pub struct Codegen {
+    counter: u32,
+}
+
+impl Codegen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lower(&mut self, program: Vec<Stmt>) -> Vec<Instr> {
+        let mut out = Vec::new();
+        for stmt in program {
+            self.lower_stmt(stmt, &mut out);
+        }
+        out
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        let name = format!("{prefix}_{}", self.counter);
+        self.counter += 1;
+        name
+    }
+
+    fn lower_stmt(&mut self, stmt: Stmt, out: &mut Vec<Instr>) {
+        match stmt {
+            Stmt::Bin(bop, a, b, dest) => out.push(Instr::Op {
+                op: fix_b(&bop, &a, &b),
+                a: a.to_num(),
+                b: b.to_num(),
+                dest: Operand::Reg(dest.to_num()),
+            }),
+            Stmt::Un(uop, a, dest) => out.push(Instr::Op {
+                op: fix_u(&uop, &a),
+                a: a.to_num(),
+                b: 0,
+                dest: Operand::Reg(dest.to_num()),
+            }),
+            Stmt::Save { addr, val } => out.push(Instr::Op {
+                op: fix_b(&SAVE, &addr, &val),
+                a: addr.to_num(),
+                b: val.to_num(),
+                dest: Operand::Reg(0),
+            }),
+            Stmt::Load { addr, reg } => out.push(Instr::Op {
+                op: LOAD + if addr.is_im() { 128 } else { 0 },
+                a: addr.to_num(),
+                b: 0,
+                dest: Operand::Reg(reg.to_num()),
+            }),
+            Stmt::Hf(reg) => out.push(Instr::Op {
+                op: HF,
+                a: 0,
+                b: 0,
+                dest: Operand::Reg(reg.to_num()),
+            }),
+            Stmt::Label(l) => out.push(Instr::Label(l)),
+            Stmt::Br { label, cond } => out.push(Instr::Branch {
+                op: fix_b(&cond.cmp, &cond.lhs, &cond.rhs),
+                a: cond.lhs.to_num(),
+                b: cond.rhs.to_num(),
+                target: label,
+            }),
+            Stmt::Args => {
+                for reg in [Reg::R1, Reg::R2, Reg::R3, Reg::R4] {
+                    out.push(Instr::Op {
+                        op: fix_b(&Bop::Sub, &Val::Reg(Reg::R5), &Val::Im(1)),
+                        a: Reg::R5.to_num(),
+                        b: 1,
+                        dest: Operand::Reg(Reg::R5.to_num()),
+                    });
+                    out.push(Instr::Op {
+                        op: fix_b(&SAVE, &Val::Reg(Reg::R5), &Val::Reg(reg)),
+                        a: Reg::R5.to_num(),
+                        b: reg.to_num(),
+                        dest: Operand::Reg(0),
+                    });
+                }
+            }
+            Stmt::Call(s) => {
+                out.push(Instr::Op {
+                    op: fix_b(&Bop::Add, &Val::Reg(Reg::P), &Val::Im(8)),
+                    a: Reg::P.to_num(),
+                    b: 8,
+                    dest: Operand::Reg(0),
+                });
+                out.push(Instr::Op {
+                    op: fix_b(&SAVE, &Val::Reg(Reg::R5), &Val::Reg(Reg::R0)),
+                    a: Reg::R5.to_num(),
+                    b: Reg::R0.to_num(),
+                    dest: Operand::Reg(0),
+                });
+                // `Display`'s text form embeds the callee label straight into this
+                // line's `a` operand ("64 {s} 0 6", an absolute add-into-P jump).
+                // `Instr`'s `a`/`b` are plain `u8`s with nowhere to carry a label,
+                // so the jump goes through the same unconditional-branch primitive
+                // `While`/`If`/`Loop` already use for their always-taken edges; the
+                // linker resolves `target` the same way regardless of caller.
+                out.push(Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: s,
+                });
+            }
+            Stmt::Ret => {
+                for reg in [Reg::R0, Reg::R4, Reg::R3, Reg::R2, Reg::R1] {
+                    out.push(Instr::Op {
+                        op: LOAD,
+                        a: Reg::R5.to_num(),
+                        b: 0,
+                        dest: Operand::Reg(reg.to_num()),
+                    });
+                    out.push(Instr::Op {
+                        op: fix_b(&Bop::Add, &Val::Reg(Reg::R5), &Val::Im(1)),
+                        a: Reg::R5.to_num(),
+                        b: 1,
+                        dest: Operand::Reg(Reg::R5.to_num()),
+                    });
+                }
+                out.push(Instr::Op {
+                    op: fix_b(&Bop::Add, &Val::Reg(Reg::R0), &Val::Im(0)),
+                    a: Reg::R0.to_num(),
+                    b: 0,
+                    dest: Operand::Reg(Reg::P.to_num()),
+                });
+            }
+            Stmt::While { cond, block } => {
+                let top = self.fresh("L");
+                let end = self.fresh("E");
+                out.push(Instr::Label(top.clone()));
+                out.push(Instr::Branch {
+                    op: fix_b(&cond.cmp.inv(), &cond.lhs, &cond.rhs),
+                    a: cond.lhs.to_num(),
+                    b: cond.rhs.to_num(),
+                    target: end.clone(),
+                });
+                for s in block {
+                    self.lower_stmt(s, out);
+                }
+                out.push(Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: top,
+                });
+                out.push(Instr::Label(end));
+            }
+            Stmt::If { cond, yes, no } => {
+                let taken = self.fresh("T");
+                let done = self.fresh("D");
+                out.push(Instr::Branch {
+                    op: fix_b(&cond.cmp, &cond.lhs, &cond.rhs),
+                    a: cond.lhs.to_num(),
+                    b: cond.rhs.to_num(),
+                    target: taken.clone(),
+                });
+                for s in no {
+                    self.lower_stmt(s, out);
+                }
+                out.push(Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: done.clone(),
+                });
+                out.push(Instr::Label(taken));
+                for s in yes {
+                    self.lower_stmt(s, out);
+                }
+                out.push(Instr::Label(done));
+            }
+            Stmt::Loop { block } => {
+                let top = self.fresh("L");
+                out.push(Instr::Label(top.clone()));
+                for s in block {
+                    self.lower_stmt(s, out);
+                }
+                out.push(Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: top,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowers_call_to_return_address_save_and_unconditional_jump() {
+        let instrs = Codegen::new().lower(vec![Stmt::Call("callee".to_string())]);
+        assert_eq!(
+            instrs,
+            vec![
+                Instr::Op {
+                    op: fix_b(&Bop::Add, &Val::Reg(Reg::P), &Val::Im(8)),
+                    a: Reg::P.to_num(),
+                    b: 8,
+                    dest: Operand::Reg(0),
+                },
+                Instr::Op {
+                    op: fix_b(&SAVE, &Val::Reg(Reg::R5), &Val::Reg(Reg::R0)),
+                    a: Reg::R5.to_num(),
+                    b: Reg::R0.to_num(),
+                    dest: Operand::Reg(0),
+                },
+                Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: "callee".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn fresh_labels_are_deterministic_and_unique() {
+        let mut gen = Codegen::new();
+        assert_eq!(gen.fresh("L"), "L_0");
+        assert_eq!(gen.fresh("L"), "L_1");
+        assert_eq!(gen.fresh("E"), "E_2");
+    }
+}
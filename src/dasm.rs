@@ -0,0 +1,177 @@
+use crate::syntax::{Bop, Cmp, Cond, Reg, Stmt, Uop, Val, HF, LOAD, SAVE};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DasmError {
+    BadLine(String),
+    BadField(String),
+    UnknownOpcode(u8),
+}
+
+/// Rebuilds the flat `Label`/`Br`/`Bin`/`Un`/`Load`/`Save` instructions that
+/// `Display` lowers `Stmt` into. Structured control flow (`While`/`If`/`Loop`)
+/// cannot be recovered, since it is flattened away before it ever reaches text.
+pub fn disassemble(src: &str) -> Result<Vec<Stmt>, DasmError> {
+    src.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(disassemble_line)
+        .collect()
+}
+
+fn disassemble_line(line: &str) -> Result<Stmt, DasmError> {
+    if let Some(name) = line.strip_prefix("label ") {
+        return Ok(Stmt::Label(name.trim().to_string()));
+    }
+
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [opcode, a, b, dest] = fields[..] else {
+        return Err(DasmError::BadLine(line.to_string()));
+    };
+    let raw: u8 = opcode
+        .parse()
+        .map_err(|_| DasmError::BadField(opcode.to_string()))?;
+    let a_num: u8 = a.parse().map_err(|_| DasmError::BadField(a.to_string()))?;
+    let b_num: u8 = b.parse().map_err(|_| DasmError::BadField(b.to_string()))?;
+
+    let lhs_im = raw & 128 != 0;
+    let rhs_im = raw & 64 != 0;
+    let op = raw & 0b0011_1111;
+
+    let lhs = |n: u8| {
+        if lhs_im {
+            Val::Im(n)
+        } else {
+            Val::Reg(reg_operand(n))
+        }
+    };
+    let rhs = |n: u8| {
+        if rhs_im {
+            Val::Im(n)
+        } else {
+            Val::Reg(reg_operand(n))
+        }
+    };
+    let dest_reg = || -> Result<Reg, DasmError> {
+        let n: u8 = dest
+            .parse()
+            .map_err(|_| DasmError::BadField(dest.to_string()))?;
+        Ok(reg_dest(n))
+    };
+
+    match op {
+        0 => Ok(Stmt::Bin(Bop::Add, lhs(a_num), rhs(b_num), dest_reg()?)),
+        1 => Ok(Stmt::Bin(Bop::Sub, lhs(a_num), rhs(b_num), dest_reg()?)),
+        2 => Ok(Stmt::Bin(Bop::And, lhs(a_num), rhs(b_num), dest_reg()?)),
+        3 => Ok(Stmt::Bin(Bop::Or, lhs(a_num), rhs(b_num), dest_reg()?)),
+        4 => Ok(Stmt::Un(Uop::Not, lhs(a_num), dest_reg()?)),
+        5 => Ok(Stmt::Bin(Bop::Xor, lhs(a_num), rhs(b_num), dest_reg()?)),
+        LOAD => Ok(Stmt::Load {
+            addr: lhs(a_num),
+            reg: dest_reg()?,
+        }),
+        SAVE => Ok(Stmt::Save {
+            addr: lhs(a_num),
+            val: rhs(b_num),
+        }),
+        8 => Ok(Stmt::Bin(Bop::Shl, lhs(a_num), rhs(b_num), dest_reg()?)),
+        9 => Ok(Stmt::Bin(Bop::Shr, lhs(a_num), rhs(b_num), dest_reg()?)),
+        10 => Ok(Stmt::Bin(Bop::Rol, lhs(a_num), rhs(b_num), dest_reg()?)),
+        11 => Ok(Stmt::Bin(Bop::Ror, lhs(a_num), rhs(b_num), dest_reg()?)),
+        12 => Ok(Stmt::Bin(Bop::Ashr, lhs(a_num), rhs(b_num), dest_reg()?)),
+        13 => Ok(Stmt::Bin(Bop::Mul, lhs(a_num), rhs(b_num), dest_reg()?)),
+        14 => Ok(Stmt::Bin(Bop::Div, lhs(a_num), rhs(b_num), dest_reg()?)),
+        HF => Ok(Stmt::Hf(dest_reg()?)),
+        16..=21 => Ok(Stmt::Br {
+            label: dest.to_string(),
+            cond: Cond {
+                lhs: lhs(a_num),
+                rhs: rhs(b_num),
+                cmp: cmp_from_num(op),
+            },
+        }),
+        _ => Err(DasmError::UnknownOpcode(raw)),
+    }
+}
+
+// Register numbers 6/7 are shared between `P`/`I`/`O` in `ToNum`; an operand
+// position can only ever have been a read (`I`), a destination only a write (`O`).
+fn reg_operand(n: u8) -> Reg {
+    match n {
+        0 => Reg::R0,
+        1 => Reg::R1,
+        2 => Reg::R2,
+        3 => Reg::R3,
+        4 => Reg::R4,
+        5 => Reg::R5,
+        6 => Reg::P,
+        _ => Reg::I,
+    }
+}
+
+fn reg_dest(n: u8) -> Reg {
+    match n {
+        0 => Reg::R0,
+        1 => Reg::R1,
+        2 => Reg::R2,
+        3 => Reg::R3,
+        4 => Reg::R4,
+        5 => Reg::R5,
+        6 => Reg::P,
+        _ => Reg::O,
+    }
+}
+
+fn cmp_from_num(n: u8) -> Cmp {
+    match n {
+        16 => Cmp::Eq,
+        17 => Cmp::Neq,
+        18 => Cmp::Lt,
+        19 => Cmp::Leq,
+        20 => Cmp::Gt,
+        _ => Cmp::Geq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_program() -> Vec<Stmt> {
+        vec![
+            Stmt::Label("start".to_string()),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R0), Val::Im(4), Reg::R1),
+            Stmt::Un(Uop::Not, Val::Reg(Reg::R1), Reg::R2),
+            Stmt::Save {
+                addr: Val::Reg(Reg::R5),
+                val: Val::Reg(Reg::R2),
+            },
+            Stmt::Load {
+                addr: Val::Im(10),
+                reg: Reg::R3,
+            },
+            Stmt::Br {
+                label: "start".to_string(),
+                cond: Cond {
+                    lhs: Val::Reg(Reg::R3),
+                    rhs: Val::Im(0),
+                    cmp: Cmp::Neq,
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn roundtrips_through_text() {
+        let program = flat_program();
+        let text = program
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rebuilt = disassemble(&text).unwrap();
+
+        let original: Vec<String> = program.iter().map(|s| format!("{s:?}")).collect();
+        let decoded: Vec<String> = rebuilt.iter().map(|s| format!("{s:?}")).collect();
+        assert_eq!(original, decoded);
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+
+use crate::syntax::{Bop, Cmp, Cond, Reg, Stmt, Uop, Val};
+
+pub const MEM_SIZE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    DivByZero,
+    UndefinedLabel(String),
+}
+
+#[derive(Debug, Default)]
+struct Registers {
+    r: [u8; 6],
+    p: u8,
+    high: u8,
+}
+
+/// Executes either the structured `Stmt` tree or its flat lowered form
+/// (nested `While`/`If`/`Loop` are expanded to `Label`/`Br` on construction).
+pub struct Machine<I> {
+    regs: Registers,
+    mem: [u8; MEM_SIZE],
+    input: I,
+    output: Vec<u8>,
+    prog: Vec<FlatStmt>,
+    labels: HashMap<String, usize>,
+    pc: usize,
+}
+
+// The subset of `Stmt` left once `While`/`If`/`Loop` have been flattened away.
+#[derive(Clone)]
+enum FlatStmt {
+    Bin(Bop, Val, Val, Reg),
+    Un(Uop, Val, Reg),
+    Hf(Reg),
+    Call(String),
+    Args,
+    Ret,
+    Save { addr: Val, val: Val },
+    Load { addr: Val, reg: Reg },
+    Label(String),
+    Br { label: String, cond: Cond },
+}
+
+fn always() -> Cond {
+    Cond {
+        lhs: Val::Im(0),
+        rhs: Val::Im(0),
+        cmp: Cmp::Eq,
+    }
+}
+
+fn flatten_into(stmt: Stmt, out: &mut Vec<FlatStmt>) {
+    match stmt {
+        Stmt::Bin(bop, a, b, dest) => out.push(FlatStmt::Bin(bop, a, b, dest)),
+        Stmt::Un(uop, a, dest) => out.push(FlatStmt::Un(uop, a, dest)),
+        Stmt::Hf(reg) => out.push(FlatStmt::Hf(reg)),
+        Stmt::Call(s) => out.push(FlatStmt::Call(s)),
+        Stmt::Args => out.push(FlatStmt::Args),
+        Stmt::Ret => out.push(FlatStmt::Ret),
+        Stmt::Save { addr, val } => out.push(FlatStmt::Save { addr, val }),
+        Stmt::Load { addr, reg } => out.push(FlatStmt::Load { addr, reg }),
+        Stmt::Label(l) => out.push(FlatStmt::Label(l)),
+        Stmt::Br { label, cond } => out.push(FlatStmt::Br { label, cond }),
+        Stmt::While { cond, block } => {
+            let stamp = out.len();
+            let top = format!("L_{stamp:x}");
+            let end = format!("E_{stamp:x}");
+            out.push(FlatStmt::Label(top.clone()));
+            out.push(FlatStmt::Br {
+                label: end.clone(),
+                cond: Cond {
+                    cmp: cond.cmp.inv(),
+                    ..cond
+                },
+            });
+            for s in block {
+                flatten_into(s, out);
+            }
+            out.push(FlatStmt::Br {
+                label: top,
+                cond: always(),
+            });
+            out.push(FlatStmt::Label(end));
+        }
+        Stmt::If { cond, yes, no } => {
+            let stamp = out.len();
+            let taken = format!("T_{stamp:x}");
+            let done = format!("D_{stamp:x}");
+            out.push(FlatStmt::Br {
+                label: taken.clone(),
+                cond,
+            });
+            for s in no {
+                flatten_into(s, out);
+            }
+            out.push(FlatStmt::Br {
+                label: done.clone(),
+                cond: always(),
+            });
+            out.push(FlatStmt::Label(taken));
+            for s in yes {
+                flatten_into(s, out);
+            }
+            out.push(FlatStmt::Label(done));
+        }
+        Stmt::Loop { block } => {
+            let stamp = out.len();
+            let top = format!("L_{stamp:x}");
+            out.push(FlatStmt::Label(top.clone()));
+            for s in block {
+                flatten_into(s, out);
+            }
+            out.push(FlatStmt::Br {
+                label: top,
+                cond: always(),
+            });
+        }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Machine<I> {
+    pub fn new(program: Vec<Stmt>, input: I) -> Self {
+        let mut prog = Vec::new();
+        for s in program {
+            flatten_into(s, &mut prog);
+        }
+        let mut labels = HashMap::new();
+        for (i, s) in prog.iter().enumerate() {
+            if let FlatStmt::Label(l) = s {
+                labels.insert(l.clone(), i);
+            }
+        }
+        Self {
+            regs: Registers::default(),
+            mem: [0; MEM_SIZE],
+            input,
+            output: Vec::new(),
+            prog,
+            labels,
+            pc: 0,
+        }
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn run(&mut self) -> Result<(), EvalError> {
+        while self.pc < self.prog.len() {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, val: &Val) -> u8 {
+        match val {
+            Val::Im(x) => *x,
+            Val::Reg(reg) => self.read_reg(*reg),
+        }
+    }
+
+    fn read_reg(&mut self, reg: Reg) -> u8 {
+        match reg {
+            Reg::R0 => self.regs.r[0],
+            Reg::R1 => self.regs.r[1],
+            Reg::R2 => self.regs.r[2],
+            Reg::R3 => self.regs.r[3],
+            Reg::R4 => self.regs.r[4],
+            Reg::R5 => self.regs.r[5],
+            Reg::P => self.regs.p,
+            Reg::I => self.input.next().unwrap_or(0),
+            Reg::O => 0,
+        }
+    }
+
+    fn write(&mut self, reg: Reg, val: u8) {
+        match reg {
+            Reg::R0 => self.regs.r[0] = val,
+            Reg::R1 => self.regs.r[1] = val,
+            Reg::R2 => self.regs.r[2] = val,
+            Reg::R3 => self.regs.r[3] = val,
+            Reg::R4 => self.regs.r[4] = val,
+            Reg::R5 => self.regs.r[5] = val,
+            Reg::P => self.regs.p = val,
+            Reg::O => self.output.push(val),
+            Reg::I => {}
+        }
+    }
+
+    fn apply_bop(&mut self, op: Bop, a: u8, b: u8) -> Result<u8, EvalError> {
+        Ok(match op {
+            Bop::Add => a.wrapping_add(b),
+            Bop::Sub => a.wrapping_sub(b),
+            Bop::And => a & b,
+            Bop::Or => a | b,
+            Bop::Xor => a ^ b,
+            Bop::Shl => a.wrapping_shl(b as u32),
+            Bop::Shr => a.wrapping_shr(b as u32),
+            Bop::Rol => a.rotate_left(b as u32),
+            Bop::Ror => a.rotate_right(b as u32),
+            Bop::Ashr => ((a as i8).wrapping_shr(b as u32)) as u8,
+            Bop::Mul => {
+                let wide = a as u16 * b as u16;
+                self.regs.high = (wide >> 8) as u8;
+                wide as u8
+            }
+            Bop::Div => {
+                if b == 0 {
+                    return Err(EvalError::DivByZero);
+                }
+                self.regs.high = a % b;
+                a / b
+            }
+        })
+    }
+
+    fn apply_cond(&mut self, cond: &Cond) -> bool {
+        let l = self.read(&cond.lhs);
+        let r = self.read(&cond.rhs);
+        match cond.cmp {
+            Cmp::Eq => l == r,
+            Cmp::Neq => l != r,
+            Cmp::Lt => l < r,
+            Cmp::Leq => l <= r,
+            Cmp::Gt => l > r,
+            Cmp::Geq => l >= r,
+        }
+    }
+
+    fn step(&mut self) -> Result<(), EvalError> {
+        match self.prog[self.pc].clone() {
+            FlatStmt::Bin(op, a, b, dest) => {
+                let (av, bv) = (self.read(&a), self.read(&b));
+                let v = self.apply_bop(op, av, bv)?;
+                self.write(dest, v);
+                self.pc += 1;
+            }
+            FlatStmt::Un(op, a, dest) => {
+                let av = self.read(&a);
+                let v = match op {
+                    Uop::Not => !av,
+                };
+                self.write(dest, v);
+                self.pc += 1;
+            }
+            FlatStmt::Save { addr, val } => {
+                let (a, v) = (self.read(&addr), self.read(&val));
+                self.mem[a as usize] = v;
+                self.pc += 1;
+            }
+            FlatStmt::Load { addr, reg } => {
+                let a = self.read(&addr);
+                let v = self.mem[a as usize];
+                self.write(reg, v);
+                self.pc += 1;
+            }
+            FlatStmt::Label(_) => {
+                self.pc += 1;
+            }
+            FlatStmt::Hf(reg) => {
+                let high = self.regs.high;
+                self.write(reg, high);
+                self.pc += 1;
+            }
+            FlatStmt::Br { label, cond } => {
+                let taken = self.apply_cond(&cond);
+                let target = *self
+                    .labels
+                    .get(&label)
+                    .ok_or(EvalError::UndefinedLabel(label))?;
+                self.pc = if taken { target } else { self.pc + 1 };
+            }
+            FlatStmt::Args => {
+                for reg in [Reg::R1, Reg::R2, Reg::R3, Reg::R4] {
+                    self.regs.r[5] = self.regs.r[5].wrapping_sub(1);
+                    let v = self.read_reg(reg);
+                    self.mem[self.regs.r[5] as usize] = v;
+                }
+                self.pc += 1;
+            }
+            FlatStmt::Call(s) => {
+                let ret = self.pc as u8 + 1;
+                // `Args` leaves R5 pointing at the slot it just filled (R4's);
+                // the return address needs its own slot below that one, or
+                // `Ret`'s pop order would clobber R4 and desync every slot
+                // after it.
+                self.regs.r[5] = self.regs.r[5].wrapping_sub(1);
+                self.mem[self.regs.r[5] as usize] = ret;
+                let target = *self.labels.get(&s).ok_or(EvalError::UndefinedLabel(s))?;
+                self.pc = target;
+            }
+            FlatStmt::Ret => {
+                let sp = self.regs.r[5];
+                self.regs.r[0] = self.mem[sp as usize];
+                self.regs.r[5] = sp.wrapping_add(1);
+                for reg in [Reg::R4, Reg::R3, Reg::R2, Reg::R1] {
+                    let sp = self.regs.r[5];
+                    let v = self.mem[sp as usize];
+                    self.write(reg, v);
+                    self.regs.r[5] = sp.wrapping_add(1);
+                }
+                self.pc = self.regs.r[0] as usize;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_and_outputs() {
+        let program = vec![
+            Stmt::Bin(Bop::Add, Val::Im(2), Val::Im(3), Reg::R0),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R0), Val::Im(0), Reg::O),
+        ];
+        let mut machine = Machine::new(program, std::iter::empty());
+        machine.run().unwrap();
+        assert_eq!(machine.output(), &[5]);
+    }
+
+    #[test]
+    fn reads_from_input_register() {
+        let program = vec![Stmt::Bin(Bop::Add, Val::Reg(Reg::I), Val::Im(1), Reg::O)];
+        let mut machine = Machine::new(program, [41].into_iter());
+        machine.run().unwrap();
+        assert_eq!(machine.output(), &[42]);
+    }
+
+    #[test]
+    fn divide_by_zero_is_an_error() {
+        let program = vec![Stmt::Bin(Bop::Div, Val::Im(1), Val::Im(0), Reg::R0)];
+        let mut machine = Machine::new(program, std::iter::empty());
+        assert_eq!(machine.run(), Err(EvalError::DivByZero));
+    }
+
+    #[test]
+    fn while_loop_counts_down_to_zero() {
+        let program = vec![Stmt::While {
+            cond: Cond {
+                lhs: Val::Reg(Reg::R0),
+                rhs: Val::Im(0),
+                cmp: Cmp::Neq,
+            },
+            block: vec![
+                Stmt::Bin(Bop::Sub, Val::Reg(Reg::R0), Val::Im(1), Reg::R0),
+                Stmt::Bin(Bop::Add, Val::Reg(Reg::R1), Val::Im(1), Reg::R1),
+            ],
+        }];
+        let mut machine = Machine::new(program, std::iter::empty());
+        machine.regs.r[0] = 3;
+        machine.run().unwrap();
+        assert_eq!(machine.regs.r[0], 0);
+        assert_eq!(machine.regs.r[1], 3);
+    }
+
+    #[test]
+    fn br_jumps_to_label_when_condition_holds() {
+        let program = vec![
+            Stmt::Br {
+                label: "skip".to_string(),
+                cond: Cond {
+                    lhs: Val::Im(1),
+                    rhs: Val::Im(1),
+                    cmp: Cmp::Eq,
+                },
+            },
+            Stmt::Bin(Bop::Add, Val::Im(1), Val::Im(0), Reg::O),
+            Stmt::Label("skip".to_string()),
+            Stmt::Bin(Bop::Add, Val::Im(2), Val::Im(0), Reg::O),
+        ];
+        let mut machine = Machine::new(program, std::iter::empty());
+        machine.run().unwrap();
+        assert_eq!(machine.output(), &[2]);
+    }
+
+    #[test]
+    fn call_preserves_args_across_a_call_and_ret() {
+        let always = Cond {
+            lhs: Val::Im(0),
+            rhs: Val::Im(0),
+            cmp: Cmp::Eq,
+        };
+        let program = vec![
+            // Jump past the callee body; `f` just returns straight away.
+            Stmt::Br {
+                label: "main".to_string(),
+                cond: always,
+            },
+            Stmt::Label("f".to_string()),
+            Stmt::Ret,
+            Stmt::Label("main".to_string()),
+            Stmt::Bin(Bop::Add, Val::Im(10), Val::Im(0), Reg::R1),
+            Stmt::Bin(Bop::Add, Val::Im(20), Val::Im(0), Reg::R2),
+            Stmt::Bin(Bop::Add, Val::Im(30), Val::Im(0), Reg::R3),
+            Stmt::Bin(Bop::Add, Val::Im(40), Val::Im(0), Reg::R4),
+            Stmt::Args,
+            Stmt::Call("f".to_string()),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R1), Val::Im(0), Reg::O),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R2), Val::Im(0), Reg::O),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R3), Val::Im(0), Reg::O),
+            Stmt::Bin(Bop::Add, Val::Reg(Reg::R4), Val::Im(0), Reg::O),
+        ];
+        let mut machine = Machine::new(program, std::iter::empty());
+        machine.regs.r[5] = 250;
+        machine.run().unwrap();
+        assert_eq!(machine.output(), &[10, 20, 30, 40]);
+        assert_eq!(machine.regs.r[5], 250);
+    }
+}
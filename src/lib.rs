@@ -0,0 +1,7 @@
+pub mod bytecode;
+pub mod codegen;
+pub mod dasm;
+pub mod eval;
+pub mod linker;
+pub mod opt;
+pub mod syntax;
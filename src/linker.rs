@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::codegen::{Instr, Operand};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+}
+
+/// Resolves `Instr::Label` directives and `Instr::Branch` targets into numeric
+/// addresses, relative to `Reg::P`, leaving a program of `Instr::Op` only.
+pub fn link(program: Vec<Instr>) -> Result<Vec<Instr>, LinkError> {
+    let mut labels = HashMap::new();
+    let mut resolved = Vec::with_capacity(program.len());
+    for instr in program {
+        match instr {
+            Instr::Label(name) => {
+                if labels.insert(name.clone(), resolved.len()).is_some() {
+                    return Err(LinkError::DuplicateLabel(name));
+                }
+            }
+            other => resolved.push(other),
+        }
+    }
+
+    for (idx, instr) in resolved.iter_mut().enumerate() {
+        let Instr::Branch { op, a, b, target } = instr else {
+            continue;
+        };
+        let (op, a, b) = (*op, *a, *b);
+        let addr = *labels
+            .get(target)
+            .ok_or_else(|| LinkError::UndefinedLabel(target.clone()))?;
+        let offset = (addr as u8).wrapping_sub(idx as u8);
+        *instr = Instr::Op {
+            op,
+            a,
+            b,
+            dest: Operand::Addr(offset),
+        };
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::Codegen;
+    use crate::syntax::{Cmp, Stmt, ToNum};
+
+    #[test]
+    fn resolves_a_call_into_caller_to_a_relative_offset() {
+        let program = vec![
+            Stmt::Call("add_one".to_string()),
+            Stmt::Label("add_one".to_string()),
+            Stmt::Ret,
+        ];
+        let instrs = Codegen::new().lower(program);
+        let linked = link(instrs).unwrap();
+
+        let Instr::Op { op, dest, .. } = linked[2] else {
+            panic!("expected the call's jump to resolve to an Instr::Op");
+        };
+        assert_eq!(op, Cmp::Eq.to_num());
+        assert_eq!(dest, Operand::Addr(1));
+    }
+
+    #[test]
+    fn rejects_duplicate_labels() {
+        let program = vec![Instr::Label("a".to_string()), Instr::Label("a".to_string())];
+        assert_eq!(
+            link(program),
+            Err(LinkError::DuplicateLabel("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_labels() {
+        let program = vec![Instr::Branch {
+            op: Cmp::Eq.to_num(),
+            a: 0,
+            b: 0,
+            target: "nowhere".to_string(),
+        }];
+        assert_eq!(
+            link(program),
+            Err(LinkError::UndefinedLabel("nowhere".to_string()))
+        );
+    }
+}
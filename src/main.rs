@@ -1,22 +1,155 @@
 use std::{
-    io::{Read, stdin},
+    fs,
+    io::{stdin, stdout, Read, Write},
     iter::repeat_n,
+    process::ExitCode,
 };
 
-use asm_leg::grammar::ProgramParser;
+use asm_leg::{
+    bytecode, codegen::Codegen, dasm, eval::Machine, grammar::ProgramParser, linker, opt,
+};
 
 const L: usize = 15;
-fn main() {
-    let mut buf = String::new();
-    stdin().read_to_string(&mut buf).unwrap();
 
-    match ProgramParser::new().parse(&buf) {
-        Ok(x) => x.into_iter().for_each(|x| {
-            println!("{x}{} # {x:?}", {
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    Asm,
+    Bin,
+    Dasm,
+}
+
+struct Cli {
+    emit: Emit,
+    run: bool,
+    opt_level: u8,
+    input: Vec<u8>,
+    in_path: Option<String>,
+    out_path: Option<String>,
+}
+
+impl Cli {
+    fn parse() -> Result<Self, String> {
+        let mut cli = Cli {
+            emit: Emit::Asm,
+            run: false,
+            opt_level: 0,
+            input: Vec::new(),
+            in_path: None,
+            out_path: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--emit" => {
+                    let mode = args.next().ok_or("--emit needs an argument")?;
+                    cli.emit = match mode.as_str() {
+                        "asm" => Emit::Asm,
+                        "bin" => Emit::Bin,
+                        "dasm" => Emit::Dasm,
+                        other => return Err(format!("unknown --emit mode: {other}")),
+                    };
+                }
+                "--run" => cli.run = true,
+                "--opt" => {
+                    let level = args.next().ok_or("--opt needs an argument")?;
+                    cli.opt_level = level
+                        .parse()
+                        .map_err(|_| format!("invalid --opt level: {level}"))?;
+                }
+                "--input" => {
+                    let text = args.next().ok_or("--input needs an argument")?;
+                    cli.input = text.into_bytes();
+                }
+                "-o" => {
+                    cli.out_path = Some(args.next().ok_or("-o needs an argument")?);
+                }
+                path => cli.in_path = Some(path.to_string()),
+            }
+        }
+        if cli.run && cli.emit != Emit::Asm {
+            return Err("--run executes the parsed program directly; it can't be combined with --emit bin/dasm".to_string());
+        }
+        if cli.opt_level != 0 && (cli.run || cli.emit != Emit::Bin) {
+            return Err("--opt only affects --emit bin; it has no effect with --run or with --emit asm/dasm".to_string());
+        }
+        Ok(cli)
+    }
+}
+
+fn read_source(cli: &Cli) -> std::io::Result<String> {
+    match &cli.in_path {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut buf = String::new();
+            stdin().read_to_string(&mut buf)?;
+            Ok(buf)
+        }
+    }
+}
+
+fn write_output(cli: &Cli, bytes: &[u8]) -> std::io::Result<()> {
+    match &cli.out_path {
+        Some(path) => fs::write(path, bytes),
+        None => stdout().write_all(bytes),
+    }
+}
+
+fn annotated_asm(program: &[asm_leg::syntax::Stmt]) -> String {
+    program
+        .iter()
+        .map(|x| {
+            format!("{x}{} # {x:?}\n", {
                 let l = x.to_string().len();
                 repeat_n(' ', if l < L { L - l } else { 0 }).collect::<String>()
             })
-        }),
-        Err(e) => println!("{e}"),
+        })
+        .collect()
+}
+
+fn run() -> Result<(), String> {
+    let cli = Cli::parse()?;
+    let src = read_source(&cli).map_err(|e| e.to_string())?;
+
+    if cli.emit == Emit::Dasm {
+        let program = dasm::disassemble(&src).map_err(|e| format!("{e:?}"))?;
+        write_output(&cli, annotated_asm(&program).as_bytes()).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let program = match ProgramParser::new().parse(&src) {
+        Ok(program) => program,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if cli.run {
+        let mut machine = Machine::new(program, cli.input.into_iter());
+        machine.run().map_err(|e| format!("{e:?}"))?;
+        write_output(&cli, machine.output()).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    match cli.emit {
+        Emit::Asm => {
+            write_output(&cli, annotated_asm(&program).as_bytes()).map_err(|e| e.to_string())?;
+        }
+        Emit::Bin => {
+            let instrs = Codegen::new().lower(program);
+            let instrs = opt::optimize(instrs, cli.opt_level);
+            let linked = linker::link(instrs).map_err(|e| format!("{e:?}"))?;
+            let bytes = bytecode::encode(&linked).map_err(|e| format!("{e:?}"))?;
+            write_output(&cli, &bytes).map_err(|e| e.to_string())?;
+        }
+        Emit::Dasm => unreachable!("handled above"),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
     }
 }
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::codegen::Instr;
+use crate::syntax::{Cmp, ToNum};
+
+// Bop/Uop opcodes with no side effects beyond their `dest` write, safe to fold.
+// `Mul`/`Div` are excluded: they also write the hidden `high` register.
+const FOLDABLE: [u8; 11] = [0, 1, 2, 3, 4, 5, 8, 9, 10, 11, 12];
+
+fn base_op(op: u8) -> u8 {
+    op & 0b0011_1111
+}
+
+fn flags(op: u8) -> (bool, bool) {
+    (op & 128 != 0, op & 64 != 0)
+}
+
+fn fold_value(base: u8, a: u8, b: u8) -> u8 {
+    match base {
+        0 => a.wrapping_add(b),
+        1 => a.wrapping_sub(b),
+        2 => a & b,
+        3 => a | b,
+        4 => !a,
+        5 => a ^ b,
+        8 => a.wrapping_shl(b as u32),
+        9 => a.wrapping_shr(b as u32),
+        10 => a.rotate_left(b as u32),
+        11 => a.rotate_right(b as u32),
+        12 => ((a as i8).wrapping_shr(b as u32)) as u8,
+        _ => unreachable!("base_op not in FOLDABLE"),
+    }
+}
+
+fn eval_cmp(base: u8, a: u8, b: u8) -> bool {
+    match base {
+        16 => a == b,
+        17 => a != b,
+        18 => a < b,
+        19 => a <= b,
+        20 => a > b,
+        21 => a >= b,
+        _ => unreachable!("base_op not a Cmp"),
+    }
+}
+
+fn is_unconditional(op: u8, a: u8, b: u8) -> bool {
+    op == Cmp::Eq.to_num() && a == 0 && b == 0
+}
+
+/// Folds `Bin`/`Un` ops over all-immediate operands into a materialized
+/// immediate, keeping the same `dest`.
+fn fold_constants(program: Vec<Instr>) -> Vec<Instr> {
+    program
+        .into_iter()
+        .map(|instr| {
+            let Instr::Op { op, a, b, dest } = instr else {
+                return instr;
+            };
+            let base = base_op(op);
+            if !FOLDABLE.contains(&base) {
+                return Instr::Op { op, a, b, dest };
+            }
+            let (a_im, b_im) = flags(op);
+            let unary = base == 4;
+            if !a_im || (!unary && !b_im) {
+                return Instr::Op { op, a, b, dest };
+            }
+            let value = fold_value(base, a, if unary { 0 } else { b });
+            Instr::Op {
+                op: 128 | 64, // Bop::Add (0) with both operands immediate
+                a: value,
+                b: 0,
+                dest,
+            }
+        })
+        .collect()
+}
+
+/// Drops branches whose `Cond` is decidable at compile time (both sides
+/// immediate): always-taken ones become an unconditional jump, the rest
+/// are removed outright.
+fn eliminate_static_branches(program: Vec<Instr>) -> Vec<Instr> {
+    program
+        .into_iter()
+        .filter_map(|instr| {
+            let Instr::Branch { op, a, b, target } = &instr else {
+                return Some(instr);
+            };
+            let (a_im, b_im) = flags(*op);
+            if !a_im || !b_im {
+                return Some(instr);
+            }
+            if eval_cmp(base_op(*op), *a, *b) {
+                Some(Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: target.clone(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// For every label, the target a branch to it should really jump to, after
+// following any chain of unconditional jumps starting right after it.
+fn resolve_forwarding(program: &[Instr]) -> HashMap<String, String> {
+    let mut after_label: HashMap<&str, usize> = HashMap::new();
+    for (i, instr) in program.iter().enumerate() {
+        if let Instr::Label(name) = instr {
+            after_label.insert(name.as_str(), i + 1);
+        }
+    }
+
+    let mut resolved = HashMap::new();
+    for name in after_label.keys() {
+        let mut target = name.to_string();
+        let mut seen = HashSet::new();
+        while seen.insert(target.clone()) {
+            let Some(&start) = after_label.get(target.as_str()) else {
+                break;
+            };
+            let mut idx = start;
+            while let Some(Instr::Label(_)) = program.get(idx) {
+                idx += 1;
+            }
+            match program.get(idx) {
+                Some(Instr::Branch {
+                    op,
+                    a,
+                    b,
+                    target: next,
+                }) if is_unconditional(*op, *a, *b) => {
+                    target = next.clone();
+                }
+                _ => break,
+            }
+        }
+        resolved.insert(name.to_string(), target);
+    }
+    resolved
+}
+
+/// Redirects every branch target through any chain of unconditional jumps it
+/// points into, and removes labels no branch targets anymore.
+fn collapse_jumps_and_dead_labels(program: Vec<Instr>) -> Vec<Instr> {
+    let forwarding = resolve_forwarding(&program);
+    let rewritten: Vec<Instr> = program
+        .into_iter()
+        .map(|instr| match instr {
+            Instr::Branch { op, a, b, target } => {
+                let target = forwarding.get(&target).cloned().unwrap_or(target);
+                Instr::Branch { op, a, b, target }
+            }
+            other => other,
+        })
+        .collect();
+
+    let used: HashSet<String> = rewritten
+        .iter()
+        .filter_map(|i| match i {
+            Instr::Branch { target, .. } => Some(target.clone()),
+            _ => None,
+        })
+        .collect();
+    rewritten
+        .into_iter()
+        .filter(|i| !matches!(i, Instr::Label(name) if !used.contains(name)))
+        .collect()
+}
+
+/// Runs the peephole/constant-folding passes. `level == 0` is a no-op so the
+/// unoptimized path stays available for debugging.
+pub fn optimize(program: Vec<Instr>, level: u8) -> Vec<Instr> {
+    if level == 0 {
+        return program;
+    }
+    let program = fold_constants(program);
+    let program = eliminate_static_branches(program);
+    collapse_jumps_and_dead_labels(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::Operand;
+
+    #[test]
+    fn level_zero_is_a_no_op() {
+        let program = vec![Instr::Op {
+            op: 128 | 0, // Bop::Add with lhs immediate
+            a: 2,
+            b: 4,
+            dest: Operand::Reg(0),
+        }];
+        assert_eq!(optimize(program.clone(), 0), program);
+    }
+
+    #[test]
+    fn folds_an_all_immediate_add() {
+        let program = vec![Instr::Op {
+            op: 128 | 64, // Bop::Add with both operands immediate
+            a: 2,
+            b: 4,
+            dest: Operand::Reg(0),
+        }];
+        let folded = optimize(program, 1);
+        assert_eq!(
+            folded,
+            vec![Instr::Op {
+                op: 128 | 64,
+                a: 6,
+                b: 0,
+                dest: Operand::Reg(0),
+            }]
+        );
+    }
+
+    #[test]
+    fn drops_an_always_false_branch() {
+        let program = vec![Instr::Branch {
+            op: 16 | 128 | 64, // Cmp::Eq, both operands immediate
+            a: 1,
+            b: 2,
+            target: "nope".to_string(),
+        }];
+        assert_eq!(optimize(program, 1), Vec::new());
+    }
+
+    #[test]
+    fn collapses_a_jump_chain_and_drops_the_dead_label() {
+        let program = vec![
+            Instr::Branch {
+                op: Cmp::Eq.to_num(),
+                a: 0,
+                b: 0,
+                target: "mid".to_string(),
+            },
+            Instr::Label("mid".to_string()),
+            Instr::Branch {
+                op: Cmp::Eq.to_num(),
+                a: 0,
+                b: 0,
+                target: "end".to_string(),
+            },
+            Instr::Label("end".to_string()),
+        ];
+        let optimized = optimize(program, 1);
+        assert_eq!(
+            optimized,
+            vec![
+                Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: "end".to_string(),
+                },
+                Instr::Branch {
+                    op: Cmp::Eq.to_num(),
+                    a: 0,
+                    b: 0,
+                    target: "end".to_string(),
+                },
+                Instr::Label("end".to_string()),
+            ]
+        );
+    }
+}
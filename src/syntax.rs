@@ -307,16 +307,16 @@ impl Val {
         matches!(self, Self::Im(..))
     }
 }
-fn fix_u<T: ToNum + Copy>(op: &T, x: &Val) -> u8 {
+pub(crate) fn fix_u<T: ToNum + Copy>(op: &T, x: &Val) -> u8 {
     op.to_num() + x.is_im().then_some(128).unwrap_or(0)
 }
-fn fix_b<T: ToNum + Copy>(op: &T, l: &Val, r: &Val) -> u8 {
+pub(crate) fn fix_b<T: ToNum + Copy>(op: &T, l: &Val, r: &Val) -> u8 {
     op.to_num() + l.is_im().then_some(128).unwrap_or(0) + r.is_im().then_some(64).unwrap_or(0)
 }
 
-const LOAD: u8 = 6;
-const SAVE: u8 = 7;
-const HF: u8 = 15;
+pub(crate) const LOAD: u8 = 6;
+pub(crate) const SAVE: u8 = 7;
+pub(crate) const HF: u8 = 15;
 
 fn get_stamp() -> u32 {
     SystemTime::now()